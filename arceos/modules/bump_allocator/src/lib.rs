@@ -1,4 +1,4 @@
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 extern crate alloc;
 use allocator::{AllocError, AllocResult, BaseAllocator, ByteAllocator, PageAllocator};
@@ -17,8 +17,14 @@ use core::ptr::NonNull;
 ///
 /// For bytes area, 'count' records number of allocations.
 /// When it goes down to ZERO, free bytes-used area.
-/// For pages area, it will never be freed!
+/// For pages area, the most-recently-allocated block can be freed by
+/// releasing it back to 'page_pos' (LIFO); anything else is leaked until the
+/// region itself is reclaimed.
 ///
+/// `add_memory` can register further, disjoint memory regions discovered
+/// during early boot; each keeps its own independent `byte_pos`/`page_pos`
+/// cursors, and allocations fall through to later regions once earlier ones
+/// are exhausted.
 
 #[inline]
 const fn align_up(val: usize, align: usize) -> usize {
@@ -29,77 +35,119 @@ const fn align_down(val: usize, align: usize) -> usize {
     (val) & !(align - 1)
 }
 
-pub struct EarlyAllocator<const PAGE_SIZE: usize> {
+/// Maximum number of discontiguous memory regions an `EarlyAllocator` can track.
+const MAX_REGIONS: usize = 4;
+
+#[derive(Clone, Copy)]
+struct Region {
     start: usize,
     end: usize,
-    count: usize,
     byte_pos: usize,
     page_pos: usize,
 }
 
-impl<const PAGE_SIZE: usize> EarlyAllocator<PAGE_SIZE> {
-    pub const fn new() -> Self {
+impl Region {
+    const fn empty() -> Self {
         Self {
             start: 0,
             end: 0,
-            count: 0,
             byte_pos: 0,
             page_pos: 0,
         }
     }
+
+    fn new(start: usize, size: usize) -> Self {
+        Self {
+            start,
+            end: start + size,
+            byte_pos: start,
+            page_pos: start + size,
+        }
+    }
+}
+
+pub struct EarlyAllocator<const PAGE_SIZE: usize> {
+    regions: [Region; MAX_REGIONS],
+    region_count: usize,
+    count: usize,
+}
+
+impl<const PAGE_SIZE: usize> EarlyAllocator<PAGE_SIZE> {
+    pub const fn new() -> Self {
+        Self {
+            regions: [Region::empty(); MAX_REGIONS],
+            region_count: 0,
+            count: 0,
+        }
+    }
 }
 
 impl<const PAGE_SIZE: usize> BaseAllocator for EarlyAllocator<PAGE_SIZE> {
     /// Initialize the allocator with a free memory region.
     fn init(&mut self, start: usize, size: usize) {
-        self.start = start;
-        self.end = start + size;
-        self.byte_pos = start;
-        self.page_pos = self.end;
+        self.regions[0] = Region::new(start, size);
+        self.region_count = 1;
         self.count = 0;
     }
 
     /// Add a free memory region to the allocator.
-    fn add_memory(&mut self, _start: usize, _size: usize) -> AllocResult {
-        unimplemented!();
+    fn add_memory(&mut self, start: usize, size: usize) -> AllocResult {
+        if self.region_count >= MAX_REGIONS {
+            return Err(AllocError::NoMemory);
+        }
+        self.regions[self.region_count] = Region::new(start, size);
+        self.region_count += 1;
+        Ok(())
     }
 }
 
 impl<const PAGE_SIZE: usize> ByteAllocator for EarlyAllocator<PAGE_SIZE> {
     /// Allocate memory with the given size (in bytes) and alignment.
     fn alloc(&mut self, layout: Layout) -> AllocResult<NonNull<u8>> {
-        let start = align_up(self.byte_pos, layout.align());
-        let next = start + layout.size();
-        if next > self.page_pos {
-            alloc::alloc::handle_alloc_error(layout);
-        } else {
-            self.byte_pos = next;
-            self.count += 1;
-            NonNull::new(start as *mut u8).ok_or(AllocError::NoMemory)
+        for region in &mut self.regions[..self.region_count] {
+            let start = align_up(region.byte_pos, layout.align());
+            let next = start + layout.size();
+            if next <= region.page_pos {
+                region.byte_pos = next;
+                self.count += 1;
+                return NonNull::new(start as *mut u8).ok_or(AllocError::NoMemory);
+            }
         }
+        alloc::alloc::handle_alloc_error(layout);
     }
 
     /// Deallocate memory at the given position, size, and alignment.
     fn dealloc(&mut self, _pos: NonNull<u8>, _layout: Layout) {
         self.count -= 1;
         if self.count == 0 {
-            self.byte_pos = self.start;
+            for region in &mut self.regions[..self.region_count] {
+                region.byte_pos = region.start;
+            }
         }
     }
 
     /// Returns total memory size in bytes.
     fn total_bytes(&self) -> usize {
-        self.end - self.start
+        self.regions[..self.region_count]
+            .iter()
+            .map(|r| r.end - r.start)
+            .sum()
     }
 
     /// Returns allocated memory size in bytes.
     fn used_bytes(&self) -> usize {
-        self.byte_pos - self.start
+        self.regions[..self.region_count]
+            .iter()
+            .map(|r| r.byte_pos - r.start)
+            .sum()
     }
 
     /// Returns available memory size in bytes.
     fn available_bytes(&self) -> usize {
-        self.page_pos - self.byte_pos
+        self.regions[..self.region_count]
+            .iter()
+            .map(|r| r.page_pos - r.byte_pos)
+            .sum()
     }
 }
 
@@ -108,32 +156,108 @@ impl<const PAGE_SIZE: usize> PageAllocator for EarlyAllocator<PAGE_SIZE> {
     /// Allocate contiguous memory pages with given count and alignment.
     fn alloc_pages(&mut self, num_pages: usize, align_pow2: usize) -> AllocResult<usize> {
         assert_eq!(align_pow2 % PAGE_SIZE, 0);
-        let next = align_down(self.page_pos - num_pages * PAGE_SIZE, PAGE_SIZE);
-        if next <= self.byte_pos {
-            Err(AllocError::NoMemory)
-        } else {
-            self.page_pos = next;
-            Ok(next)
+        for region in &mut self.regions[..self.region_count] {
+            let next = align_down(region.page_pos - num_pages * PAGE_SIZE, PAGE_SIZE);
+            if next > region.byte_pos {
+                region.page_pos = next;
+                return Ok(next);
+            }
         }
-    } 
+        Err(AllocError::NoMemory)
+    }
 
     /// Deallocate contiguous memory pages with given position and count.
-    fn dealloc_pages(&mut self, _pos: usize, _num_pages: usize) {
-        unimplemented!();
+    ///
+    /// Only the most-recently-allocated block of a region (the one sitting
+    /// right at its `page_pos`) can actually be reclaimed, since a bump
+    /// allocator keeps no record of individual allocations; freeing anything
+    /// else is a no-op and the pages stay leaked until the region is reused.
+    fn dealloc_pages(&mut self, pos: usize, num_pages: usize) {
+        for region in &mut self.regions[..self.region_count] {
+            if pos == region.page_pos {
+                region.page_pos += num_pages * PAGE_SIZE;
+                return;
+            }
+        }
     }
 
     /// Returns the total number of memory pages.
     fn total_pages(&self) -> usize {
-        (self.end - self.start) / PAGE_SIZE
+        self.regions[..self.region_count]
+            .iter()
+            .map(|r| (r.end - r.start) / PAGE_SIZE)
+            .sum()
     }
 
     /// Returns the number of allocated memory pages.
     fn used_pages(&self) -> usize {
-        (self.end - self.page_pos) / PAGE_SIZE
+        self.regions[..self.region_count]
+            .iter()
+            .map(|r| (r.end - r.page_pos) / PAGE_SIZE)
+            .sum()
     }
 
     /// Returns the number of available memory pages.
     fn available_pages(&self) -> usize {
-        (self.page_pos - self.byte_pos) / PAGE_SIZE
+        self.regions[..self.region_count]
+            .iter()
+            .map(|r| (r.page_pos - r.byte_pos) / PAGE_SIZE)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE_SIZE: usize = 0x1000;
+
+    #[test]
+    fn dealloc_pages_reclaims_the_most_recent_block() {
+        let mut a: EarlyAllocator<PAGE_SIZE> = EarlyAllocator::new();
+        a.init(0x1000_0000, 16 * PAGE_SIZE);
+        let pos = a.alloc_pages(2, PAGE_SIZE).unwrap();
+        assert_eq!(a.available_pages(), 14);
+        a.dealloc_pages(pos, 2);
+        assert_eq!(a.available_pages(), 16);
+    }
+
+    #[test]
+    fn dealloc_pages_is_a_noop_for_a_non_lifo_block() {
+        let mut a: EarlyAllocator<PAGE_SIZE> = EarlyAllocator::new();
+        a.init(0x1000_0000, 16 * PAGE_SIZE);
+        let first = a.alloc_pages(2, PAGE_SIZE).unwrap();
+        let _second = a.alloc_pages(2, PAGE_SIZE).unwrap();
+        assert_eq!(a.available_pages(), 12);
+        // `first` is no longer at `page_pos` (`_second` is), so this leaks
+        // it rather than reclaiming it.
+        a.dealloc_pages(first, 2);
+        assert_eq!(a.available_pages(), 12);
+    }
+
+    #[test]
+    fn add_memory_falls_through_to_the_next_region_once_exhausted() {
+        let mut a: EarlyAllocator<PAGE_SIZE> = EarlyAllocator::new();
+        a.init(0x1000_0000, 2 * PAGE_SIZE);
+        a.add_memory(0x2000_0000, 2 * PAGE_SIZE).unwrap();
+        assert_eq!(a.total_pages(), 4);
+
+        let first = a.alloc_pages(1, PAGE_SIZE).unwrap();
+        assert_eq!(first, 0x1000_1000);
+        // The first region has one page left, but `alloc_pages` never lowers
+        // `page_pos` all the way down to `byte_pos`, so this must come out of
+        // the second region.
+        let second = a.alloc_pages(1, PAGE_SIZE).unwrap();
+        assert_eq!(second, 0x2000_1000);
+    }
+
+    #[test]
+    fn add_memory_beyond_max_regions_fails() {
+        let mut a: EarlyAllocator<PAGE_SIZE> = EarlyAllocator::new();
+        a.init(0x1000_0000, PAGE_SIZE);
+        a.add_memory(0x2000_0000, PAGE_SIZE).unwrap();
+        a.add_memory(0x3000_0000, PAGE_SIZE).unwrap();
+        a.add_memory(0x4000_0000, PAGE_SIZE).unwrap();
+        assert!(a.add_memory(0x5000_0000, PAGE_SIZE).is_err());
     }
 }
@@ -0,0 +1,262 @@
+//! Optional corruption-detection wrapper around [`HashMap`], ported from the
+//! servo `DiagnosticHashMap` idea.
+//!
+//! Gated behind the `hashmap-diagnostics` feature so release kernels pay
+//! nothing for it. Every stored value carries a canary word that is checked
+//! on every read, and a small ring journal of recent operations is kept to
+//! help localize the heap corruption bugs that are otherwise invisible in
+//! `no_std`.
+
+use core::borrow::Borrow;
+use core::hash::{BuildHasher, Hash};
+
+use super::hash_map::HashMap;
+use super::random_state::RandomState;
+
+/// Canary value written alongside every stored value, checked on every read.
+#[cfg(target_pointer_width = "64")]
+const CANARY: usize = 0x42cafe9942cafe99;
+#[cfg(target_pointer_width = "32")]
+const CANARY: usize = 0x42cafe99;
+
+/// Pattern taken as a mark of freed memory; if a canary reads as this, the
+/// slot is reported as freed out from under the map.
+#[cfg(target_pointer_width = "64")]
+const POISON: usize = 0xdeaddeaddeaddead;
+#[cfg(target_pointer_width = "32")]
+const POISON: usize = 0xdeaddead;
+
+const JOURNAL_LEN: usize = 16;
+
+/// The kind of operation recorded in a [`DiagnosticHashMap`]'s journal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Insert,
+    Remove,
+    GetOrInsertWith,
+    Clear,
+}
+
+#[derive(Clone, Copy)]
+struct JournalEntry {
+    op: Op,
+    /// Hash of the key involved, or `0` for key-less operations like `Clear`.
+    key_hash: u64,
+}
+
+/// A debug-build `HashMap` wrapper that detects use-after-free and wild
+/// writes into its backing store.
+///
+/// Every value is stored as `(canary, V)`. The canary is checked on every
+/// `get`/`get_mut`/`remove`, and a panic fires if it no longer matches,
+/// telling apart a clean logical bug (key absent) from memory corruption.
+/// Recent mutating operations are kept in a small ring journal to help
+/// narrow down when the corruption happened, and [`set_readonly`] can be
+/// used to turn further mutation into a hard error.
+///
+/// [`set_readonly`]: DiagnosticHashMap::set_readonly
+pub struct DiagnosticHashMap<K, V, S = RandomState> {
+    base: HashMap<K, (usize, V), S>,
+    journal: [Option<JournalEntry>; JOURNAL_LEN],
+    journal_pos: usize,
+    readonly: bool,
+}
+
+impl<K: Hash + Eq, V> DiagnosticHashMap<K, V> {
+    /// Creates an empty `DiagnosticHashMap`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<K: Hash + Eq, V> Default for DiagnosticHashMap<K, V> {
+    fn default() -> Self {
+        Self {
+            base: HashMap::new(),
+            journal: [None; JOURNAL_LEN],
+            journal_pos: 0,
+            readonly: false,
+        }
+    }
+}
+
+impl<K, V, S> DiagnosticHashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// When set, any mutating call (`insert`, `remove`, `clear`, ...) panics
+    /// instead of touching the map.
+    pub fn set_readonly(&mut self, readonly: bool) {
+        self.readonly = readonly;
+    }
+
+    /// Returns the journal entries recorded so far, oldest first.
+    pub fn journal(&self) -> impl Iterator<Item = (&'static str, u64)> + '_ {
+        let len = self.journal.len();
+        (0..len)
+            .map(move |i| self.journal[(self.journal_pos + i) % len])
+            .filter_map(|entry| entry.map(|e| (e.op.as_str(), e.key_hash)))
+    }
+
+    fn hash_key(&self, key: &K) -> u64 {
+        self.base.hasher().hash_one(key)
+    }
+
+    fn record(&mut self, op: Op, key_hash: u64) {
+        assert!(!self.readonly, "DiagnosticHashMap: mutation on a read-only map");
+        self.journal[self.journal_pos] = Some(JournalEntry { op, key_hash });
+        self.journal_pos = (self.journal_pos + 1) % JOURNAL_LEN;
+    }
+
+    fn check_canary(canary: usize) {
+        assert_ne!(
+            canary, POISON,
+            "DiagnosticHashMap: use-after-free detected (value slot reads as POISON)"
+        );
+        assert_eq!(
+            canary, CANARY,
+            "DiagnosticHashMap: corrupted entry (canary overwritten, want {CANARY:#x}, got {canary:#x})"
+        );
+    }
+
+    /// Inserts a key-value pair into the map.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let key_hash = self.hash_key(&key);
+        self.record(Op::Insert, key_hash);
+        self.base.insert(key, (CANARY, value)).map(|(canary, v)| {
+            Self::check_canary(canary);
+            v
+        })
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    pub fn get<Q: ?Sized>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        self.base.get(key).map(|(canary, v)| {
+            Self::check_canary(*canary);
+            v
+        })
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key.
+    pub fn get_mut<Q: ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        self.base.get_mut(key).map(|(canary, v)| {
+            Self::check_canary(*canary);
+            v
+        })
+    }
+
+    /// Removes a key from the map, returning the value at the key if it was
+    /// previously in the map.
+    pub fn remove<Q: ?Sized>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        self.record(Op::Remove, self.base.hasher().hash_one(key));
+        self.base.remove(key).map(|(canary, v)| {
+            Self::check_canary(canary);
+            v
+        })
+    }
+
+    /// Returns a reference to the value for the given key, inserting it by
+    /// calling `f` if it is absent.
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, f: F) -> &mut V {
+        let key_hash = self.hash_key(&key);
+        self.record(Op::GetOrInsertWith, key_hash);
+        let (canary, value) = self
+            .base
+            .entry(key)
+            .or_insert_with(|| (CANARY, f()));
+        Self::check_canary(*canary);
+        value
+    }
+
+    /// Clears the map, removing all key-value pairs.
+    pub fn clear(&mut self) {
+        self.record(Op::Clear, 0);
+        self.base.clear();
+    }
+
+    /// Returns the number of elements in the map.
+    pub fn len(&self) -> usize {
+        self.base.len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.base.is_empty()
+    }
+}
+
+impl Op {
+    fn as_str(self) -> &'static str {
+        match self {
+            Op::Insert => "insert",
+            Op::Remove => "remove",
+            Op::GetOrInsertWith => "get_or_insert_with",
+            Op::Clear => "clear",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_get_round_trip() {
+        let mut map: DiagnosticHashMap<&str, i32> = DiagnosticHashMap::new();
+        assert_eq!(map.insert("a", 1), None);
+        assert_eq!(map.get("a"), Some(&1));
+        assert_eq!(map.insert("a", 2), Some(1));
+        assert_eq!(map.get("a"), Some(&2));
+    }
+
+    #[test]
+    fn journal_records_operations_in_order_oldest_first() {
+        let mut map: DiagnosticHashMap<&str, i32> = DiagnosticHashMap::new();
+        map.insert("a", 1);
+        map.insert("b", 2);
+        map.remove("a");
+        map.clear();
+        let ops: alloc::vec::Vec<_> = map.journal().map(|(op, _)| op).collect();
+        assert_eq!(ops, ["insert", "insert", "remove", "clear"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "mutation on a read-only map")]
+    fn set_readonly_panics_on_mutation() {
+        let mut map: DiagnosticHashMap<&str, i32> = DiagnosticHashMap::new();
+        map.set_readonly(true);
+        map.insert("a", 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "corrupted entry")]
+    fn get_panics_on_corrupted_canary() {
+        let mut map: DiagnosticHashMap<&str, i32> = DiagnosticHashMap::new();
+        map.insert("a", 1);
+        // Simulate heap corruption: stomp the canary word directly.
+        map.base.get_mut("a").unwrap().0 = 0xdead_beef;
+        map.get("a");
+    }
+
+    #[test]
+    #[should_panic(expected = "use-after-free")]
+    fn get_panics_on_poison_canary() {
+        let mut map: DiagnosticHashMap<&str, i32> = DiagnosticHashMap::new();
+        map.insert("a", 1);
+        map.base.get_mut("a").unwrap().0 = POISON;
+        map.get("a");
+    }
+}
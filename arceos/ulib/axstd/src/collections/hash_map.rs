@@ -1,4 +1,3 @@
-use core::borrow::Borrow;
 use core::fmt;
 use core::hash::{BuildHasher, Hash};
 use core::iter::FusedIterator;
@@ -6,6 +5,7 @@ use core::ops::Index;
 use hashbrown;
 
 pub use super::random_state::RandomState;
+pub use hashbrown::{Equivalent, TryReserveError};
 
 /// A hash map implemented using the hashbrown crate, not depending on the standard library.
 pub struct HashMap<K, V, S = RandomState> {
@@ -63,10 +63,15 @@ impl<K: Hash + Eq, V, S: BuildHasher> HashMap<K, V, S> {
     }
 
     /// Returns a reference to the value corresponding to the key.
+    ///
+    /// Bounded on [`Equivalent<K>`] rather than
+    /// [`Borrow`](core::borrow::Borrow): the blanket impl hashbrown ships
+    /// accepts exactly the same `Q` as `Borrow` would today, but callers can
+    /// implement `Equivalent<K>` directly for a borrowed view of a composite
+    /// key without needing a matching `Borrow` impl to exist for it.
     pub fn get<Q: ?Sized>(&self, k: &Q) -> Option<&V>
     where
-        K: Borrow<Q>,
-        Q: Hash + Eq,
+        Q: Hash + Equivalent<K>,
     {
         self.base.get(k)
     }
@@ -74,8 +79,7 @@ impl<K: Hash + Eq, V, S: BuildHasher> HashMap<K, V, S> {
     /// Returns a mutable reference to the value corresponding to the key.
     pub fn get_mut<Q: ?Sized>(&mut self, k: &Q) -> Option<&mut V>
     where
-        K: Borrow<Q>,
-        Q: Hash + Eq,
+        Q: Hash + Equivalent<K>,
     {
         self.base.get_mut(k)
     }
@@ -83,8 +87,7 @@ impl<K: Hash + Eq, V, S: BuildHasher> HashMap<K, V, S> {
     /// Returns `true` if the map contains a value for the specified key.
     pub fn contains_key<Q: ?Sized>(&self, k: &Q) -> bool
     where
-        K: Borrow<Q>,
-        Q: Hash + Eq,
+        Q: Hash + Equivalent<K>,
     {
         self.base.contains_key(k)
     }
@@ -100,12 +103,19 @@ impl<K: Hash + Eq, V, S: BuildHasher> HashMap<K, V, S> {
     /// Removes a key from the map, returning the value at the key if the key was previously in the map.
     pub fn remove<Q: ?Sized>(&mut self, k: &Q) -> Option<V>
     where
-        K: Borrow<Q>,
-        Q: Hash + Eq,
+        Q: Hash + Equivalent<K>,
     {
         self.base.remove(k)
     }
 
+    /// Gets the given key's corresponding entry in the map for in-place manipulation.
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, S> {
+        match self.base.entry(key) {
+            hashbrown::hash_map::Entry::Occupied(base) => Entry::Occupied(OccupiedEntry { base }),
+            hashbrown::hash_map::Entry::Vacant(base) => Entry::Vacant(VacantEntry { base }),
+        }
+    }
+
     /// Returns a reference to the map's [`BuildHasher`].
     pub fn hasher(&self) -> &S {
         self.base.hasher()
@@ -116,6 +126,16 @@ impl<K: Hash + Eq, V, S: BuildHasher> HashMap<K, V, S> {
         self.base.reserve(additional)
     }
 
+    /// Tries to reserve capacity for at least `additional` more elements.
+    ///
+    /// Unlike [`reserve`](Self::reserve), this does not abort on failure. It
+    /// returns an error instead, so callers that manage untrusted or bursty
+    /// key sets (e.g. filesystem inode tables) can recover from an
+    /// allocation failure instead of panicking.
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.base.try_reserve(additional)
+    }
+
     /// Shrinks the capacity of the map as much as possible.
     pub fn shrink_to_fit(&mut self) {
         self.base.shrink_to_fit()
@@ -141,6 +161,40 @@ impl<K: Hash + Eq, V, S: BuildHasher> HashMap<K, V, S> {
             base: self.base.iter(),
         }
     }
+
+    /// Retains only the elements specified by the predicate.
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        self.base.retain(f)
+    }
+
+    /// Clears the map, returning all key-value pairs as an iterator. Keeps the
+    /// allocated memory for reuse.
+    pub fn drain(&mut self) -> Drain<'_, K, V> {
+        Drain {
+            base: self.base.drain(),
+        }
+    }
+
+    /// Creates an iterator which uses a closure to determine if an element should
+    /// be removed.
+    ///
+    /// If the closure returns `true`, the element is removed from the map and
+    /// yielded. If the closure returns `false`, or panics, the element remains
+    /// in the map and will not be yielded.
+    ///
+    /// Elements are visited lazily as the iterator is consumed; any entries not
+    /// visited before the iterator is dropped remain in the map.
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, K, V, F>
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        ExtractIf {
+            base: self.base.extract_if(pred),
+        }
+    }
 }
 
 impl<K: Hash + Eq, V> Default for HashMap<K, V> {
@@ -194,8 +248,8 @@ where
 
 impl<K, Q: ?Sized, V, S> Index<&Q> for HashMap<K, V, S>
 where
-    K: Eq + Hash + Borrow<Q>,
-    Q: Eq + Hash,
+    K: Eq + Hash,
+    Q: Eq + Hash + Equivalent<K>,
     S: BuildHasher,
 {
     type Output = V;
@@ -210,6 +264,133 @@ where
     }
 }
 
+/// A view into a single entry in a map, which may either be vacant or occupied.
+///
+/// This `enum` is constructed from the [`entry`] method on [`HashMap`].
+///
+/// [`entry`]: HashMap::entry
+pub enum Entry<'a, K, V, S> {
+    /// An occupied entry.
+    Occupied(OccupiedEntry<'a, K, V, S>),
+    /// A vacant entry.
+    Vacant(VacantEntry<'a, K, V, S>),
+}
+
+impl<'a, K, V, S> Entry<'a, K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Ensures a value is in the entry by inserting the default if empty, and returns
+    /// a mutable reference to the value in the entry.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the result of the default function
+    /// if empty, and returns a mutable reference to the value in the entry.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Provides in-place mutable access to an occupied entry before any
+    /// potential inserts into the map.
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+}
+
+impl<'a, K, V: Default, S> Entry<'a, K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Ensures a value is in the entry by inserting the default value if empty,
+    /// and returns a mutable reference to the value in the entry.
+    pub fn or_default(self) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(V::default()),
+        }
+    }
+}
+
+/// A view into an occupied entry in a `HashMap`. It is part of the [`Entry`] enum.
+pub struct OccupiedEntry<'a, K, V, S> {
+    base: hashbrown::hash_map::OccupiedEntry<'a, K, V, S>,
+}
+
+impl<'a, K, V, S> OccupiedEntry<'a, K, V, S> {
+    /// Gets a reference to the key in the entry.
+    pub fn key(&self) -> &K {
+        self.base.key()
+    }
+
+    /// Gets a reference to the value in the entry.
+    pub fn get(&self) -> &V {
+        self.base.get()
+    }
+
+    /// Gets a mutable reference to the value in the entry.
+    pub fn get_mut(&mut self) -> &mut V {
+        self.base.get_mut()
+    }
+
+    /// Converts the entry into a mutable reference to its value, with a lifetime
+    /// bound to the map itself.
+    pub fn into_mut(self) -> &'a mut V {
+        self.base.into_mut()
+    }
+
+    /// Sets the value of the entry, and returns the entry's old value.
+    pub fn insert(&mut self, value: V) -> V {
+        self.base.insert(value)
+    }
+
+    /// Takes the value out of the entry, and removes it from the map.
+    pub fn remove(self) -> V {
+        self.base.remove()
+    }
+}
+
+/// A view into a vacant entry in a `HashMap`. It is part of the [`Entry`] enum.
+pub struct VacantEntry<'a, K, V, S> {
+    base: hashbrown::hash_map::VacantEntry<'a, K, V, S>,
+}
+
+impl<'a, K, V, S> VacantEntry<'a, K, V, S> {
+    /// Gets a reference to the key that would be used when inserting a value
+    /// through the `VacantEntry`.
+    pub fn key(&self) -> &K {
+        self.base.key()
+    }
+
+    /// Takes ownership of the key.
+    pub fn into_key(self) -> K {
+        self.base.into_key()
+    }
+}
+
+impl<'a, K: Hash, V, S: BuildHasher> VacantEntry<'a, K, V, S> {
+    /// Sets the value of the entry with the `VacantEntry`'s key, and returns a
+    /// mutable reference to it.
+    pub fn insert(self, value: V) -> &'a mut V {
+        self.base.insert(value)
+    }
+}
+
 /// An iterator over the entries of a `HashMap`.
 pub struct Iter<'a, K: 'a, V: 'a> {
     base: hashbrown::hash_map::Iter<'a, K, V>,
@@ -330,6 +511,57 @@ impl<K, V> ExactSizeIterator for IntoIter<K, V> {
 
 impl<K, V> FusedIterator for IntoIter<K, V> {}
 
+/// A draining iterator over the entries of a `HashMap`.
+pub struct Drain<'a, K: 'a, V: 'a> {
+    base: hashbrown::hash_map::Drain<'a, K, V>,
+}
+
+impl<K, V> Iterator for Drain<'_, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.base.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.base.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for Drain<'_, K, V> {
+    fn len(&self) -> usize {
+        self.base.len()
+    }
+}
+
+impl<K, V> FusedIterator for Drain<'_, K, V> {}
+
+/// A lazy iterator that removes and yields the entries of a `HashMap` matching
+/// a predicate, produced by [`HashMap::extract_if`].
+pub struct ExtractIf<'a, K, V, F>
+where
+    F: FnMut(&K, &mut V) -> bool,
+{
+    base: hashbrown::hash_map::ExtractIf<'a, K, V, F>,
+}
+
+impl<K, V, F> Iterator for ExtractIf<'_, K, V, F>
+where
+    F: FnMut(&K, &mut V) -> bool,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.base.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.base.size_hint()
+    }
+}
+
+impl<K, V, F> FusedIterator for ExtractIf<'_, K, V, F> where F: FnMut(&K, &mut V) -> bool {}
+
 // From iterator implementation
 impl<K, V, S> FromIterator<(K, V)> for HashMap<K, V, S>
 where
@@ -352,4 +584,82 @@ where
     fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
         self.base.extend(iter);
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_or_insert_inserts_on_vacant() {
+        let mut map: HashMap<&str, i32> = HashMap::new();
+        *map.entry("a").or_insert(1) += 1;
+        assert_eq!(map["a"], 2);
+    }
+
+    #[test]
+    fn entry_or_insert_does_not_overwrite_occupied() {
+        let mut map: HashMap<&str, i32> = HashMap::new();
+        *map.entry("a").or_insert(1) += 1;
+        *map.entry("a").or_insert(100) += 1;
+        assert_eq!(map["a"], 3);
+    }
+
+    #[test]
+    fn entry_or_default_uses_default_value() {
+        let mut map: HashMap<&str, i32> = HashMap::new();
+        assert_eq!(*map.entry("a").or_default(), 0);
+        *map.entry("a").or_default() += 5;
+        assert_eq!(map["a"], 5);
+    }
+
+    #[test]
+    fn entry_and_modify_only_touches_occupied() {
+        let mut map: HashMap<&str, i32> = HashMap::new();
+        map.entry("a").and_modify(|v| *v += 1).or_insert(1);
+        map.entry("a").and_modify(|v| *v += 1).or_insert(1);
+        assert_eq!(map["a"], 2);
+    }
+
+    #[test]
+    fn retain_keeps_only_matching_entries() {
+        let mut map: HashMap<i32, i32> = (0..10).map(|i| (i, i)).collect();
+        map.retain(|k, _| k % 2 == 0);
+        assert_eq!(map.len(), 5);
+        assert!(map.keys().all(|k| k % 2 == 0));
+    }
+
+    #[test]
+    fn drain_empties_the_map_and_yields_every_pair() {
+        let mut map: HashMap<i32, i32> = (0..5).map(|i| (i, i * 10)).collect();
+        let mut drained: alloc::vec::Vec<_> = map.drain().collect();
+        drained.sort();
+        assert_eq!(drained, [(0, 0), (1, 10), (2, 20), (3, 30), (4, 40)]);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn extract_if_removes_matching_and_leaves_the_rest() {
+        let mut map: HashMap<i32, i32> = (0..10).map(|i| (i, i)).collect();
+        let mut extracted: alloc::vec::Vec<_> = map.extract_if(|k, _| k % 2 == 0).collect();
+        extracted.sort();
+        assert_eq!(extracted, [(0, 0), (2, 2), (4, 4), (6, 6), (8, 8)]);
+        assert_eq!(map.len(), 5);
+        assert!(map.keys().all(|k| k % 2 != 0));
+    }
+
+    #[test]
+    fn try_reserve_succeeds_and_grows_capacity() {
+        let mut map: HashMap<i32, i32> = HashMap::new();
+        assert!(map.try_reserve(16).is_ok());
+        assert!(map.capacity() >= 16);
+        map.insert(1, 1);
+        assert_eq!(map[&1], 1);
+    }
+
+    #[test]
+    fn try_reserve_rejects_an_overflowing_request() {
+        let mut map: HashMap<i32, i32> = HashMap::new();
+        assert!(map.try_reserve(usize::MAX).is_err());
+    }
+}
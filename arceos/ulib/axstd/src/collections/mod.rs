@@ -1,6 +1,10 @@
+#[cfg(feature = "hashmap-diagnostics")]
+mod diagnostic;
 mod hash_map;
 mod random_state;
 
+#[cfg(feature = "hashmap-diagnostics")]
+pub use self::diagnostic::DiagnosticHashMap;
 pub use self::hash_map::HashMap;
 
 #[cfg(feature = "alloc")]